@@ -0,0 +1,145 @@
+//! User-facing configuration, loaded from `STORAGE_DIR/config.toml`. Every
+//! field is optional, so a missing file or a partial one simply falls back
+//! to dobble's built-in defaults.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{LAST_FM_API_KEY, LAST_FM_API_SECRET, LOOP_TIME, PUSH_QUEUE_INTERVAL};
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Key file to read API credentials from when the config file neither sets
+/// them directly nor points `key_file` elsewhere. Anything still missing
+/// after that falls back to the credentials compiled into the binary.
+const DEFAULT_KEY_FILE_NAME: &str = "api-key.toml";
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    credentials: Credentials,
+    push_queue_interval_secs: Option<u64>,
+    loop_time_secs: Option<u64>,
+    scrobble_threshold_ceiling_secs: Option<u64>,
+    pub players: PlayerFilter,
+    pub filters: Vec<FilterRule>,
+}
+
+/// A single ad/junk filter rule: a regex matched against `field`, which
+/// either rewrites the field (if `replace` is set, using `$1`-style capture
+/// references) or marks the track as ignored so it never reaches
+/// `now_playing` or the scrobble threshold.
+#[derive(Debug, Deserialize, Clone)]
+pub struct FilterRule {
+    pub field: FilterField,
+    pub pattern: String,
+    #[serde(default)]
+    pub ignore: bool,
+    #[serde(default)]
+    pub replace: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterField {
+    Title,
+    Artist,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+struct Credentials {
+    api_key: Option<String>,
+    api_secret: Option<String>,
+    key_file: Option<PathBuf>,
+}
+
+/// An allow/deny list of MPRIS player bus names (e.g.
+/// `org.mpris.MediaPlayer2.spotify`). If `allow` is non-empty only those
+/// players are considered; otherwise every player is considered except
+/// those in `deny`.
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct PlayerFilter {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl PlayerFilter {
+    pub fn permits(&self, bus_name: &str) -> bool {
+        if !self.allow.is_empty() {
+            return self.allow.iter().any(|name| name == bus_name);
+        }
+
+        !self.deny.iter().any(|name| name == bus_name)
+    }
+}
+
+impl Config {
+    pub fn load(storage_dir: &Path) -> Self {
+        let path = storage_dir.join(CONFIG_FILE_NAME);
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Failed to parse config file {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Resolves the API key and secret, falling back to the compiled-in
+    /// credentials for any not found. The key file is read on every call;
+    /// nothing is cached.
+    pub fn credentials(&self, storage_dir: &Path) -> (String, String) {
+        let (api_key, api_secret) = self.credentials.resolve(storage_dir);
+
+        (
+            api_key.unwrap_or_else(|| LAST_FM_API_KEY.to_string()),
+            api_secret.unwrap_or_else(|| LAST_FM_API_SECRET.to_string()),
+        )
+    }
+
+    pub fn push_queue_interval(&self) -> Duration {
+        self.push_queue_interval_secs
+            .map_or(PUSH_QUEUE_INTERVAL, Duration::from_secs)
+    }
+
+    pub fn loop_time(&self) -> Duration {
+        self.loop_time_secs.map_or(LOOP_TIME, Duration::from_secs)
+    }
+
+    pub fn scrobble_threshold_ceiling(&self) -> Duration {
+        self.scrobble_threshold_ceiling_secs
+            .map_or(crate::SCROBBLE_THRESHOLD_CEILING, Duration::from_secs)
+    }
+}
+
+impl Credentials {
+    /// Resolves the API key and secret, preferring values set directly in
+    /// the config file, then a key file (either the one pointed at by
+    /// `key_file`, or `STORAGE_DIR/api-key.toml`), and leaving the compiled
+    /// default in place otherwise.
+    fn resolve(&self, storage_dir: &Path) -> (Option<String>, Option<String>) {
+        if self.api_key.is_some() && self.api_secret.is_some() {
+            return (self.api_key.clone(), self.api_secret.clone());
+        }
+
+        let key_file = self
+            .key_file
+            .clone()
+            .unwrap_or_else(|| storage_dir.join(DEFAULT_KEY_FILE_NAME));
+
+        let from_file = std::fs::read_to_string(&key_file)
+            .ok()
+            .and_then(|contents| toml::from_str::<Self>(&contents).ok())
+            .unwrap_or_default();
+
+        (
+            self.api_key.clone().or(from_file.api_key),
+            self.api_secret.clone().or(from_file.api_secret),
+        )
+    }
+}
@@ -0,0 +1,88 @@
+//! Applies user-configured regex rules to observed tracks, to recognize and
+//! skip ads and other non-music items.
+
+use regex::Regex;
+
+use crate::config::{FilterField, FilterRule};
+use crate::Track;
+
+pub struct CompiledRule {
+    field: FilterField,
+    pattern: Regex,
+    ignore: bool,
+    replace: Option<String>,
+}
+
+/// Rules dobble ships by default, ahead of anything from the user's config.
+/// Strips the Plex `▶ ` now-playing prefix.
+fn default_rules() -> Vec<FilterRule> {
+    vec![FilterRule {
+        field: FilterField::Title,
+        pattern: "^\u{25b6}\\s*".to_string(),
+        ignore: false,
+        replace: Some(String::new()),
+    }]
+}
+
+/// Compiles the default rules plus the user's configured filter rules,
+/// skipping (and logging) any with an invalid pattern rather than failing
+/// startup over it.
+pub fn compile_rules(user_rules: &[FilterRule]) -> Vec<CompiledRule> {
+    default_rules()
+        .into_iter()
+        .chain(user_rules.iter().cloned())
+        .filter_map(|rule| match Regex::new(&rule.pattern) {
+            Ok(pattern) => Some(CompiledRule {
+                field: rule.field,
+                pattern,
+                ignore: rule.ignore,
+                replace: rule.replace.clone(),
+            }),
+            Err(e) => {
+                eprintln!("Ignoring invalid filter pattern {:?}: {}", rule.pattern, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Applies only the title-rewriting rules to a raw title, before a `Track`
+/// exists for the full `apply` to run against.
+pub fn rewrite_title(rules: &[CompiledRule], title: &str) -> String {
+    let mut title = title.to_string();
+
+    for rule in rules {
+        if rule.field == FilterField::Title {
+            if let Some(replace) = &rule.replace {
+                if rule.pattern.is_match(&title) {
+                    title = rule.pattern.replace(&title, replace.as_str()).into_owned();
+                }
+            }
+        }
+    }
+
+    title
+}
+
+/// Applies every compiled rule to `track`, rewriting matched fields in
+/// place. Returns `true` if any matching rule marked the track as ignored.
+pub fn apply(rules: &[CompiledRule], track: &mut Track) -> bool {
+    let mut ignored = false;
+
+    for rule in rules {
+        let field = match rule.field {
+            FilterField::Title => &mut track.track,
+            FilterField::Artist => &mut track.artist,
+        };
+
+        if rule.pattern.is_match(field) {
+            ignored |= rule.ignore;
+
+            if let Some(replace) = &rule.replace {
+                *field = rule.pattern.replace(field, replace.as_str()).into_owned();
+            }
+        }
+    }
+
+    ignored
+}
@@ -0,0 +1,142 @@
+//! A local SQLite record of every track the daemon has observed, independent
+//! of whether it ever made it to Last.fm. Lets a user audit their own
+//! listening history, or see which plays failed to scrobble, entirely
+//! offline via the `query` subcommand.
+
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+
+use crate::Track;
+
+const DB_FILE_NAME: &str = "history.sqlite3";
+
+lazy_static::lazy_static! {
+    // A single shared connection, so concurrent writes from the main loop
+    // and the queue-pushing thread serialize on this mutex instead of
+    // racing as separate SQLite connections and hitting "database is
+    // locked".
+    static ref CONNECTION: Mutex<Connection> = Mutex::new(open());
+}
+
+fn open() -> Connection {
+    let path = crate::STORAGE_DIR.join(DB_FILE_NAME);
+    let conn = Connection::open(&path)
+        .unwrap_or_else(|e| panic!("Failed to open history database {}: {}", path.display(), e));
+
+    conn.busy_timeout(Duration::from_secs(5))
+        .expect("Failed to set history database busy timeout");
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS plays (
+            id INTEGER PRIMARY KEY,
+            artist TEXT NOT NULL,
+            track TEXT NOT NULL,
+            album TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            reached_threshold INTEGER NOT NULL DEFAULT 0,
+            pushed INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )
+    .expect("Failed to initialize history database schema");
+
+    conn
+}
+
+fn started_at_secs(track: &Track) -> i64 {
+    track
+        .started_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs() as i64
+}
+
+/// Records a newly observed track. Called as soon as a track becomes the
+/// current one, before it's known whether it'll reach the scrobble
+/// threshold or push successfully.
+pub fn record_play(track: &Track) {
+    let result = CONNECTION.lock().unwrap().execute(
+        "INSERT INTO plays (artist, track, album, started_at) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            track.artist,
+            track.track,
+            track.album,
+            started_at_secs(track)
+        ],
+    );
+
+    if let Err(e) = result {
+        eprintln!("Failed to record play in local history: {}", e);
+    }
+}
+
+/// Marks a previously recorded play as having reached the scrobble
+/// threshold.
+pub fn mark_reached_threshold(track: &Track) {
+    let result = CONNECTION.lock().unwrap().execute(
+        "UPDATE plays SET reached_threshold = 1
+         WHERE artist = ?1 AND track = ?2 AND started_at = ?3",
+        params![track.artist, track.track, started_at_secs(track)],
+    );
+
+    if let Err(e) = result {
+        eprintln!("Failed to update local history: {}", e);
+    }
+}
+
+/// Marks a previously recorded play as pushed to Last.fm (or not, if the
+/// immediate push failed and it was queued for a later retry).
+pub fn mark_pushed(track: &Track, pushed: bool) {
+    let result = CONNECTION.lock().unwrap().execute(
+        "UPDATE plays SET pushed = ?1
+         WHERE artist = ?2 AND track = ?3 AND started_at = ?4",
+        params![pushed, track.artist, track.track, started_at_secs(track)],
+    );
+
+    if let Err(e) = result {
+        eprintln!("Failed to update local history: {}", e);
+    }
+}
+
+/// Runs a user-supplied SQL query against the local history database and
+/// prints the results as tab-separated rows.
+pub fn run_query(sql: &str) -> rusqlite::Result<()> {
+    let conn = CONNECTION.lock().unwrap();
+    let mut statement = conn.prepare(sql)?;
+
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    println!("{}", columns.join("\t"));
+
+    let column_count = columns.len();
+    let mut rows = statement.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let values: Vec<String> = (0..column_count)
+            .map(|i| {
+                row.get::<_, rusqlite::types::Value>(i)
+                    .map(|value| format_value(&value))
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        println!("{}", values.join("\t"));
+    }
+
+    Ok(())
+}
+
+fn format_value(value: &rusqlite::types::Value) -> String {
+    match value {
+        rusqlite::types::Value::Null => String::new(),
+        rusqlite::types::Value::Integer(v) => v.to_string(),
+        rusqlite::types::Value::Real(v) => v.to_string(),
+        rusqlite::types::Value::Text(v) => v.clone(),
+        rusqlite::types::Value::Blob(_) => "<blob>".to_string(),
+    }
+}
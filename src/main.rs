@@ -2,15 +2,22 @@
 use anyhow::Result;
 use mpris::{Metadata, Player, PlayerFinder};
 use rustfm_scrobble::{Scrobble, Scrobbler};
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Mutex};
 use std::{
     convert::TryFrom,
     io::Read,
     thread::sleep,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 use thiserror::Error;
 
+mod config;
+mod filter;
+mod history;
+
+use config::{Config, PlayerFilter};
+use filter::CompiledRule;
+
 const LAST_FM_API_KEY: &str = "401615b0bba90b796964290b7c9ecc36";
 const LAST_FM_API_SECRET: &str = "353a68a2d4dfa9a0378e01be16efbaf5";
 
@@ -23,8 +30,29 @@ const WAIT_FOR_PLAYER_TIME: Duration = Duration::from_secs(5);
 /// Amount of time to sleep whilst watching for events from an active player.
 const LOOP_TIME: Duration = Duration::from_secs(1);
 
-/// Amount of time to wait before scrobbling a track.
-const SCROBBLE_THRESHOLD: Duration = Duration::from_secs(10);
+/// Last.fm's cap on how long a track must play before it's eligible to be
+/// scrobbled, regardless of length: a track counts once it has played for
+/// half its length or this long, whichever comes first.
+const SCROBBLE_THRESHOLD_CEILING: Duration = Duration::from_secs(4 * 60);
+
+/// Last.fm doesn't scrobble tracks shorter than this at all.
+const MIN_SCROBBLE_LENGTH: Duration = Duration::from_secs(30);
+
+/// Fallback threshold for players that don't expose a track length via
+/// `Metadata::length()`. Falling back to `SCROBBLE_THRESHOLD_CEILING` here
+/// would mean such a player never scrobbles anything shorter than 4
+/// minutes of continuous play, a big behavior change from the old flat 10s
+/// constant; this keeps a low floor instead so lengthless players keep
+/// scrobbling promptly.
+const SCROBBLE_THRESHOLD_FALLBACK: Duration = Duration::from_secs(30);
+
+/// Starting delay before retrying a failed queue push, doubled on every
+/// consecutive failure.
+const BACKOFF_BASE: Duration = Duration::from_secs(60);
+
+/// Upper bound on the backoff delay, so a prolonged outage doesn't push
+/// retries out indefinitely.
+const BACKOFF_CEILING: Duration = Duration::from_secs(30 * 60);
 
 lazy_static::lazy_static! {
     static ref STORAGE_DIR: std::path::PathBuf = {
@@ -33,7 +61,76 @@ lazy_static::lazy_static! {
         path
     };
 
-    static ref SCROBBLE_QUEUE: Mutex<Vec<Track>> = Mutex::<Vec<Track>>::default();
+    static ref QUEUE_JOURNAL_PATH: std::path::PathBuf = STORAGE_DIR.join("scrobble-queue.json");
+
+    static ref CONFIG: Config = Config::load(&STORAGE_DIR);
+
+    static ref FILTER_RULES: Vec<CompiledRule> = filter::compile_rules(&CONFIG.filters);
+
+    static ref SCROBBLE_QUEUE: Mutex<Vec<Track>> = Mutex::new(load_queue_journal());
+
+    static ref QUEUE_BACKOFF: Mutex<Backoff> = Mutex::<Backoff>::default();
+}
+
+/// Tracks consecutive failures to push the scrobble queue, so retries back
+/// off exponentially instead of hammering Last.fm during an outage.
+struct Backoff {
+    failures: u32,
+    next_retry: Instant,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            failures: 0,
+            next_retry: Instant::now(),
+        }
+    }
+}
+
+impl Backoff {
+    /// Whether enough time has passed since the last failure to retry.
+    fn ready(&self) -> bool {
+        Instant::now() >= self.next_retry
+    }
+
+    /// Records a failed push, scheduling the next retry after `retry_after`
+    /// if Last.fm suggested a wait, or after the next doubled delay
+    /// otherwise.
+    fn record_failure(&mut self, retry_after: Option<Duration>) {
+        let delay = retry_after.unwrap_or_else(|| {
+            BACKOFF_BASE
+                .checked_mul(1 << self.failures.min(16))
+                .unwrap_or(BACKOFF_CEILING)
+        });
+
+        self.failures += 1;
+        self.next_retry = Instant::now() + delay.min(BACKOFF_CEILING);
+    }
+
+    /// Resets the backoff after a successful push.
+    fn record_success(&mut self) {
+        self.failures = 0;
+        self.next_retry = Instant::now();
+    }
+}
+
+/// Looks for a suggested wait embedded in a Last.fm rate-limit error (e.g.
+/// "rate limit exceeded, try again in 45 seconds") and returns it if found.
+fn parse_retry_after(error: &str) -> Option<Duration> {
+    let lower = error.to_lowercase();
+
+    if !lower.contains("rate limit") && !lower.contains("try later") && !lower.contains("try again")
+    {
+        return None;
+    }
+
+    lower.split_whitespace().find_map(|word| {
+        word.trim_end_matches(|c: char| !c.is_ascii_digit())
+            .parse::<u64>()
+            .ok()
+            .map(Duration::from_secs)
+    })
 }
 
 #[derive(Error, Debug)]
@@ -42,18 +139,39 @@ enum Error {
     MissingMetadata(&'static str),
 }
 
-#[derive(Clone, Debug, Eq)]
+#[derive(Clone, Debug, Eq, serde::Serialize, serde::Deserialize)]
 struct Track {
     artist: String,
     track: String,
     album: String,
     scrobbled: bool,
     playing_for: Duration,
+    started_at: SystemTime,
+    length: Option<Duration>,
 }
 
 impl Track {
     pub fn as_scrobble(&self) -> Scrobble {
-        Scrobble::new(&self.artist, &self.track, &self.album)
+        let timestamp = self
+            .started_at
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        Scrobble::new(&self.artist, &self.track, &self.album).with_timestamp(timestamp)
+    }
+
+    /// The amount of time this track must have played for before it's
+    /// eligible to be scrobbled: half its length, or `ceiling`, whichever is
+    /// shorter. Returns `None` if the track is too short to ever be
+    /// scrobbled, or `SCROBBLE_THRESHOLD_FALLBACK` if the length isn't
+    /// known at all.
+    pub fn scrobble_threshold(&self, ceiling: Duration) -> Option<Duration> {
+        match self.length {
+            Some(length) if length < MIN_SCROBBLE_LENGTH => None,
+            Some(length) => Some((length / 2).min(ceiling)),
+            None => Some(SCROBBLE_THRESHOLD_FALLBACK),
+        }
     }
 }
 
@@ -67,7 +185,8 @@ impl TryFrom<Metadata> for Track {
     type Error = anyhow::Error;
 
     fn try_from(metadata: Metadata) -> Result<Self> {
-        let mut track = metadata.title().ok_or(Error::MissingMetadata("title"))?;
+        let title = metadata.title().ok_or(Error::MissingMetadata("title"))?;
+        let mut track = filter::rewrite_title(&FILTER_RULES, title);
 
         let mut artist = metadata
             .artists()
@@ -76,50 +195,208 @@ impl TryFrom<Metadata> for Track {
         if artist == "" {
             let mut split = track.splitn(2, " - ");
 
-            artist = match split.next() {
-                Some(v) if v.starts_with("\u{25b6} ") => v["\u{25b6} ".len()..].to_string(), // quick fix for plex
-                Some(v) => v.to_string(),
-                None => return Err(Error::MissingMetadata("artist split from title").into()),
-            };
-
-            track = split
+            let first = split
+                .next()
+                .ok_or(Error::MissingMetadata("artist split from title"))?
+                .to_string();
+            let rest = split
                 .next()
-                .ok_or(Error::MissingMetadata("artist split from title"))?;
+                .ok_or(Error::MissingMetadata("artist split from title"))?
+                .to_string();
+
+            artist = first;
+            track = rest;
         }
 
         Ok(Self {
-            track: track.to_string(),
+            track,
             artist,
             album: metadata.album_name().unwrap_or("").to_string(),
             scrobbled: false,
             playing_for: Duration::from_secs(0),
+            started_at: SystemTime::now(),
+            length: metadata.length(),
+        })
+    }
+}
+
+/// Loads any scrobbles left over from a previous run, so a crash or restart
+/// doesn't drop plays that were queued because of a failed push.
+fn load_queue_journal() -> Vec<Track> {
+    let contents = match std::fs::read_to_string(&*QUEUE_JOURNAL_PATH) {
+        Ok(v) => v,
+        Err(_) => return Vec::new(),
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(track) => Some(track),
+            Err(e) => {
+                eprintln!("Failed to parse queued scrobble from journal: {}", e);
+                None
+            }
         })
+        .collect()
+}
+
+/// Appends a single track to the on-disk journal, so it survives a restart
+/// even if the process exits before the queue is next flushed.
+fn append_to_queue_journal(track: &Track) {
+    use std::io::Write;
+
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&*QUEUE_JOURNAL_PATH)
+        .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(track)?));
+
+    if let Err(e) = result {
+        eprintln!("Failed to write queued scrobble to journal: {}", e);
     }
 }
 
-/// Blocks while waiting for a player.
-fn get_player(finder: &PlayerFinder) -> Player {
+/// Rewrites the journal to match the given queue, truncating it entirely
+/// once every queued scrobble has been pushed successfully.
+fn rewrite_queue_journal(queue: &[Track]) {
+    let contents = queue
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<serde_json::Result<Vec<_>>>()
+        .map(|lines| lines.join("\n"));
+
+    let result = match contents {
+        Ok(contents) if contents.is_empty() => std::fs::write(&*QUEUE_JOURNAL_PATH, ""),
+        Ok(contents) => std::fs::write(&*QUEUE_JOURNAL_PATH, contents + "\n"),
+        Err(e) => {
+            eprintln!("Failed to serialize scrobble queue journal: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to rewrite scrobble queue journal: {}", e);
+    }
+}
+
+/// Blocks while waiting for a player the given filter permits.
+///
+/// `find_active()` only ever returns a single, heuristically-active player,
+/// so if the filter denies it there's no way to fall through to a permitted
+/// one even if it's also running. Iterating `find_all()` instead lets the
+/// filter actually select among every player currently on the bus.
+fn get_player(finder: &PlayerFinder, filter: &PlayerFilter) -> Player {
     loop {
-        if let Ok(player) = finder.find_active() {
-            return player;
-        } else {
-            sleep(WAIT_FOR_PLAYER_TIME);
+        let permitted = finder
+            .find_all()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|player| filter.permits(player.bus_name()));
+
+        match permitted {
+            Some(player) => return player,
+            None => sleep(WAIT_FOR_PLAYER_TIME),
         }
     }
 }
 
+/// An update delivered from a [`watch_player_events`] thread: either a
+/// signal forwarded from the player's MPRIS event stream, or notice that the
+/// player has gone away and a replacement needs to be found.
+enum PlayerUpdate {
+    Event(mpris::Event),
+    Ended,
+}
+
+/// Spawns a thread that opens its own connection to the given player's bus
+/// name and forwards its MPRIS events, so the main loop can react to track
+/// and status changes as they happen instead of polling for them.
+fn watch_player_events(bus_name: String) -> mpsc::Receiver<PlayerUpdate> {
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let finder = match PlayerFinder::new() {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        let player = match finder
+            .find_all()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|player| player.bus_name() == bus_name)
+        {
+            Some(v) => v,
+            None => return,
+        };
+
+        let events = match player.events() {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        for event in events {
+            match event {
+                Ok(event) if tx.send(PlayerUpdate::Event(event)).is_ok() => {}
+                _ => break,
+            }
+        }
+
+        let _ = tx.send(PlayerUpdate::Ended);
+    });
+
+    rx
+}
+
 /// Sets the given track as now playing
 fn now_playing(scrobbler: &Scrobbler, track: &Track) -> Result<()> {
     scrobbler.now_playing(&track.as_scrobble())?;
     Ok(())
 }
 
+/// Reads whatever is already playing on a newly acquired player and treats
+/// it as the current tune. `Player::events()` only yields events on
+/// *subsequent* changes, so without this, whatever was already playing when
+/// dobble started (or when it picked up a replacement player) would never
+/// be set now-playing, recorded to history, or scrobbled until the track
+/// next changed.
+fn seed_tune(scrobbler: &Scrobbler, player: &Player) -> Option<Track> {
+    if player.get_playback_status().ok()? != mpris::PlaybackStatus::Playing {
+        return None;
+    }
+
+    let mut track = Track::try_from(player.get_metadata().ok()?).ok()?;
+
+    if filter::apply(&FILTER_RULES, &mut track) {
+        return None;
+    }
+
+    if let Err(e) = now_playing(scrobbler, &track) {
+        eprintln!("Setting now playing failed: {}", e);
+    }
+
+    history::record_play(&track);
+
+    Some(track)
+}
+
 /// Scrobbles the given track or places it in the queue if scrobbling failed.
-fn scrobble(scrobbler: &Scrobbler, track: &Track) {
+///
+/// Returns whether the track was pushed immediately, or queued for later.
+fn scrobble(scrobbler: &Scrobbler, track: &Track) -> bool {
     if let Err(e) = scrobbler.scrobble(&track.as_scrobble()) {
         // scrobbling failed, lets queue it for later
         eprintln!("Failed to scrobble track, adding to queue: {:?}", e);
-        SCROBBLE_QUEUE.lock().unwrap().push(track.clone());
+
+        // hold the lock across the journal append and the in-memory push so
+        // it can't interleave with push_queued_scrobbles() clearing and
+        // rewriting the journal, which would otherwise lose this track.
+        let mut queue = SCROBBLE_QUEUE.lock().unwrap();
+        append_to_queue_journal(track);
+        queue.push(track.clone());
+        false
+    } else {
+        true
     }
 }
 
@@ -130,19 +407,17 @@ fn scrobble(scrobbler: &Scrobbler, track: &Track) {
 /// will be blocked, and may possibly be lost if the push takes longer than
 /// the length of the track.
 fn push_queued_scrobbles(scrobbler: Arc<Scrobbler>) {
-    let should_run = !SCROBBLE_QUEUE.lock().unwrap().is_empty();
+    let should_run =
+        !SCROBBLE_QUEUE.lock().unwrap().is_empty() && QUEUE_BACKOFF.lock().unwrap().ready();
 
     if should_run {
         std::thread::spawn(move || {
             let mut queue = SCROBBLE_QUEUE.lock().unwrap();
 
-            if queue.len() == 1 {
-                if let Some(track) = queue.get(0) {
-                    match scrobbler.scrobble(&track.as_scrobble()) {
-                        Ok(_) => queue.clear(),
-                        Err(e) => eprintln!("Failed to push queued track: {}", e),
-                    }
-                }
+            let result = if queue.len() == 1 {
+                queue.get(0).map_or(Ok(()), |track| {
+                    scrobbler.scrobble(&track.as_scrobble()).map(|_| ())
+                })
             } else {
                 let batch = queue
                     .iter()
@@ -150,9 +425,25 @@ fn push_queued_scrobbles(scrobbler: Arc<Scrobbler>) {
                     .collect::<Vec<Scrobble>>()
                     .into();
 
-                match scrobbler.scrobble_batch(&batch) {
-                    Ok(_) => queue.clear(),
-                    Err(e) => eprintln!("Failed to push queued batch: {}", e),
+                scrobbler.scrobble_batch(&batch).map(|_| ())
+            };
+
+            match result {
+                Ok(_) => {
+                    for track in queue.iter() {
+                        history::mark_pushed(track, true);
+                    }
+
+                    queue.clear();
+                    rewrite_queue_journal(&queue);
+                    QUEUE_BACKOFF.lock().unwrap().record_success();
+                }
+                Err(e) => {
+                    eprintln!("Failed to push queued scrobbles: {}", e);
+                    QUEUE_BACKOFF
+                        .lock()
+                        .unwrap()
+                        .record_failure(parse_retry_after(&e.to_string()));
                 }
             }
         });
@@ -164,7 +455,7 @@ struct AuthToken {
     token: String,
 }
 
-fn authenticate_lastfm(scrobbler: &mut Scrobbler) -> Result<()> {
+fn authenticate_lastfm(scrobbler: &mut Scrobbler, api_key: &str) -> Result<()> {
     let key_file = STORAGE_DIR.join("session-key");
 
     // if the key file exists authenticate with that
@@ -176,10 +467,10 @@ fn authenticate_lastfm(scrobbler: &mut Scrobbler) -> Result<()> {
     // get a token from last.fm and ask the user to authenticate with it
     let token: AuthToken = reqwest::blocking::get(&format!(
         "https://ws.audioscrobbler.com/2.0/?method=auth.gettoken&format=json&api_key={}",
-        LAST_FM_API_KEY
+        api_key
     ))?
     .json()?;
-    println!("Please visit the following link and hit any key once allowed: http://www.last.fm/api/auth/?api_key={}&token={}", LAST_FM_API_KEY, token.token);
+    println!("Please visit the following link and hit any key once allowed: http://www.last.fm/api/auth/?api_key={}&token={}", api_key, token.token);
     std::io::stdin().read_exact(&mut [0])?;
 
     // authenticate using the token and write it to the key
@@ -204,84 +495,118 @@ fn main() {
         std::process::exit(1);
     }
 
-    let mut scrobbler = Scrobbler::new(LAST_FM_API_KEY, LAST_FM_API_SECRET);
-    if let Err(e) = authenticate_lastfm(&mut scrobbler) {
+    // `dobble query <sql>` runs a one-off query against the local play
+    // history database instead of starting the daemon.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(("query", sql)) = args.split_first().map(|(cmd, rest)| (cmd.as_str(), rest)) {
+        if let Err(e) = history::run_query(&sql.join(" ")) {
+            eprintln!("Query failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let (api_key, api_secret) = CONFIG.credentials(&STORAGE_DIR);
+    let mut scrobbler = Scrobbler::new(&api_key, &api_secret);
+    if let Err(e) = authenticate_lastfm(&mut scrobbler, &api_key) {
         eprintln!("Failed to authenticate to Last.fm: {}", e);
         std::process::exit(1);
     }
     let scrobbler = Arc::new(scrobbler);
 
     let player_finder = PlayerFinder::new().expect("Could not connect to D-Bus");
-    let mut player = get_player(&player_finder);
+    let mut player = get_player(&player_finder, &CONFIG.players);
+    let mut events = watch_player_events(player.bus_name().to_string());
 
-    let mut tune: Option<Track> = None;
+    let mut playback_status = player
+        .get_playback_status()
+        .unwrap_or(mpris::PlaybackStatus::Stopped);
+    let mut tune = seed_tune(&scrobbler, &player);
 
     let mut last_check = Instant::now();
     let mut last_pushed_queue = Instant::now();
 
     loop {
-        sleep(LOOP_TIME);
-
-        // push any scrobbles that have been queued every PUSH_QUEUE_INTERVAL
-        if last_pushed_queue.elapsed() >= PUSH_QUEUE_INTERVAL {
-            last_pushed_queue = Instant::now();
-            push_queued_scrobbles(scrobbler.clone());
-        }
-
-        // calculate the time since the last iteration
-        let now = Instant::now();
-        let duration_since_last_check = now.duration_since(last_check);
-        last_check = now;
-
-        // replace the player if the current one disconnected
-        if !player.is_running() {
-            player = get_player(&player_finder);
-            tune = None;
-        }
-
-        // skip to the next iteration
-        match player.get_playback_status() {
-            Ok(mpris::PlaybackStatus::Playing) => {}
-            Ok(mpris::PlaybackStatus::Stopped) => {
+        match events.recv_timeout(CONFIG.loop_time()) {
+            // a track or status transition happened: handle it immediately
+            // rather than waiting for the next tick.
+            Ok(PlayerUpdate::Event(mpris::Event::Playing)) => {
+                playback_status = mpris::PlaybackStatus::Playing;
+
+                // a bare Playing transition (e.g. resuming from pause, or
+                // the first event after a Stopped/filtered track cleared
+                // `tune`) isn't followed by a TrackChanged, so without this
+                // the current track would never be seeded.
+                if tune.is_none() {
+                    tune = seed_tune(&scrobbler, &player);
+                }
+            }
+            Ok(PlayerUpdate::Event(mpris::Event::Paused)) => {
+                playback_status = mpris::PlaybackStatus::Paused
+            }
+            Ok(PlayerUpdate::Event(mpris::Event::Stopped)) => {
+                playback_status = mpris::PlaybackStatus::Stopped;
                 tune = None;
-                continue;
             }
-            _ => continue,
-        }
-
-        // collect track metadata
-        let metadata = match player.get_metadata() {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("Failed to collect track metadata: {:?}", e);
-                continue;
+            Ok(PlayerUpdate::Event(mpris::Event::TrackChanged(metadata))) => {
+                if let Ok(mut currently_playing) = Track::try_from(metadata) {
+                    if filter::apply(&FILTER_RULES, &mut currently_playing) {
+                        // matched an ad/junk filter rule: treat it as if
+                        // nothing is playing rather than scrobbling it.
+                        tune = None;
+                    } else if tune.as_ref() != Some(&currently_playing) {
+                        if let Err(e) = now_playing(&scrobbler, &currently_playing) {
+                            eprintln!("Setting now playing failed: {}", e);
+                        }
+
+                        history::record_play(&currently_playing);
+                        tune = Some(currently_playing);
+                    }
+                }
+            }
+            Ok(PlayerUpdate::Event(_)) => {}
+
+            // the player disappeared: find a replacement and start watching it.
+            Ok(PlayerUpdate::Ended) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                player = get_player(&player_finder, &CONFIG.players);
+                events = watch_player_events(player.bus_name().to_string());
+                playback_status = player
+                    .get_playback_status()
+                    .unwrap_or(mpris::PlaybackStatus::Stopped);
+                tune = seed_tune(&scrobbler, &player);
+                last_check = Instant::now();
             }
-        };
-
-        // convert the currently playing song to a `Track`
-        let currently_playing = match Track::try_from(metadata) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
 
-        // if the current tune is the same one playing in the last iteration,
-        // increment the time playing and maybe scrobble. otherwise, replace the
-        // playing tune.
-        match &mut tune {
-            Some(tune) if *tune == currently_playing => {
-                tune.playing_for += duration_since_last_check;
+            // no event arrived within the tick: just accumulate playing time
+            // and handle the periodic queue push.
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                let duration_since_last_check = now.duration_since(last_check);
+                last_check = now;
 
-                if tune.playing_for >= SCROBBLE_THRESHOLD && !tune.scrobbled {
-                    scrobble(&scrobbler, &tune);
-                    tune.scrobbled = true;
+                if last_pushed_queue.elapsed() >= CONFIG.push_queue_interval() {
+                    last_pushed_queue = Instant::now();
+                    push_queued_scrobbles(scrobbler.clone());
                 }
-            }
-            _ => {
-                if let Err(e) = now_playing(&scrobbler, &currently_playing) {
-                    eprintln!("Setting now playing failed: {}", e);
+
+                if playback_status != mpris::PlaybackStatus::Playing {
+                    continue;
                 }
 
-                tune = Some(currently_playing);
+                if let Some(tune) = &mut tune {
+                    tune.playing_for += duration_since_last_check;
+
+                    let reached_threshold = tune
+                        .scrobble_threshold(CONFIG.scrobble_threshold_ceiling())
+                        .is_some_and(|threshold| tune.playing_for >= threshold);
+
+                    if reached_threshold && !tune.scrobbled {
+                        history::mark_reached_threshold(tune);
+                        let pushed = scrobble(&scrobbler, tune);
+                        history::mark_pushed(tune, pushed);
+                        tune.scrobbled = true;
+                    }
+                }
             }
         }
     }